@@ -0,0 +1,17 @@
+//! Program entrypoint wiring, compiled unless the `no-entrypoint` feature is enabled.
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, pubkey::Pubkey,
+};
+
+use crate::processor::process_instruction as process;
+
+entrypoint!(process_instruction);
+
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    process(program_id, accounts, instruction_data)
+}
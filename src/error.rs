@@ -0,0 +1,61 @@
+//! Error types returned by the staking/reward-distribution program.
+
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StakingError {
+    #[error("account is already initialized")]
+    AlreadyInitialized,
+
+    #[error("account is not initialized")]
+    NotInitialized,
+
+    #[error("reward pool does not hold enough lamports for this distribution")]
+    InsufficientRewardPool,
+
+    #[error("accounts passed to the instruction do not match what it expects")]
+    AccountMismatch,
+
+    #[error("instruction data could not be parsed")]
+    InvalidInstructionData,
+
+    #[error("stake history account has not been initialized")]
+    StakeHistoryNotInitialized,
+
+    #[error("reward pool has more than one partition; use DistributeEpochPartition instead")]
+    DistributeRewardsRequiresSinglePartition,
+
+    #[error("the prior epoch's distribution has not finished all of its partitions yet")]
+    PreviousDistributionIncomplete,
+
+    #[error("partition must be paid in order and can only be paid once per epoch")]
+    PartitionOutOfOrder,
+
+    #[error("a staker account does not belong to the partition being distributed")]
+    StakerNotInPartition,
+
+    #[error("commission must be between 0 and 100")]
+    InvalidCommission,
+
+    #[error("the operator account passed does not match RewardPool::operator")]
+    OperatorMismatch,
+
+    #[error("the stake's lockup has not yet expired")]
+    LockupNotExpired,
+
+    #[error("withdraw authority must be the stake's owner or custodian")]
+    WithdrawAuthorityMismatch,
+
+    #[error("the custodian account passed does not match StakingAccount::lockup.custodian")]
+    CustodianMismatch,
+
+    #[error("reward pool must have at least one partition")]
+    InvalidPartitionCount,
+}
+
+impl From<StakingError> for ProgramError {
+    fn from(e: StakingError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
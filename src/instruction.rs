@@ -0,0 +1,107 @@
+//! Instruction definitions for the staking/reward-distribution program.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+/// Instructions supported by the program. The borsh-derived discriminant
+/// (the first byte of the instruction data) is the "instr byte" referenced
+/// throughout the tests and docs, so new variants must always be appended
+/// rather than inserted, to keep existing byte values stable.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum StakingInstruction {
+    /// 0: Create a staking account for `stake_amount` and begin its warmup.
+    ///
+    /// The account does not earn the full `stake_amount` immediately; its
+    /// effective stake ramps up epoch over epoch per [`crate::state::StakeHistory`].
+    /// The stake is locked up until both `unlock_epoch` and `unlock_timestamp`
+    /// have passed, unless `custodian` signs (see [`StakingInstruction::Withdraw`]).
+    /// `point_value_observed` is seeded from the reward pool's current
+    /// `cumulative_point_value`, so this stake doesn't retroactively earn
+    /// rewards for epochs that elapsed before it existed.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` the new `StakingAccount`, already allocated by the caller
+    /// 1. `[signer]` the staking user
+    /// 2. `[writable]` the shared `StakeHistory` account
+    /// 3. `[]` Clock sysvar
+    /// 4. `[]` the `RewardPool` this stake will earn against
+    CreateStakingAccount {
+        stake_amount: u64,
+        unlock_epoch: u64,
+        unlock_timestamp: i64,
+        custodian: Pubkey,
+    },
+
+    /// 1: Initialize a `RewardPool` account with the lamports it already holds,
+    /// divided into `num_partitions` for epoch distribution. `commission`
+    /// (0-100) of each epoch's rewards is skimmed to `operator` before the
+    /// pro-rata staker split.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` the new `RewardPool`, already allocated and funded
+    InitializeRewardPool {
+        total_rewards: u64,
+        num_partitions: u32,
+        operator: Pubkey,
+        commission: u8,
+    },
+
+    /// 2: Distribute the reward pool's lamports to stakers, weighted by each
+    /// staker's effective stake and the point value it has accrued since its
+    /// `point_value_observed` checkpoint, after skimming the operator's
+    /// commission. Only usable while the pool has a single partition; larger
+    /// staker sets must use [`StakingInstruction::DistributeEpochPartition`]
+    /// instead.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` the `RewardPool`
+    /// 1. `[writable]` the shared `StakeHistory` account (replayed forward to the
+    ///    current epoch before being read)
+    /// 2. `[]` Clock sysvar
+    /// 3. `[writable]` the pool's `operator` account
+    /// 4..N `[writable]` pairs of (`StakingAccount`, destination user account),
+    ///      interleaved: staking_account_0, user_0, staking_account_1, user_1, ...
+    DistributeRewards,
+
+    /// 3: Pay out exactly one partition of stakers for the current epoch's
+    /// distribution and advance `RewardPool::distribution_cursor`. The first
+    /// call observed in a new epoch snapshots the pool's totals (skimming the
+    /// operator's commission at that point); starting a new epoch's
+    /// distribution is rejected until the prior epoch's cursor has reached
+    /// `num_partitions`, and paying a partition out of order (or one already
+    /// paid) is rejected too.
+    ///
+    /// Accounts expected: same shape as `DistributeRewards`, but only the
+    /// stakers hashing into `partition_index` may be included.
+    DistributeEpochPartition { partition_index: u32 },
+
+    /// 4: Withdraw a stake's full balance (principal plus any rent-exempt
+    /// reserve) to `destination` and close the `StakingAccount`.
+    ///
+    /// Allowed when `authority` is the stake's `owner` and the lockup has
+    /// expired (per [`crate::state::Lockup::is_expired`]), or when
+    /// `authority` is the lockup's `custodian` regardless of expiry. Whatever
+    /// portion of the stake had already become effective begins cooldown in
+    /// `StakeHistory` rather than vanishing from the cluster total outright;
+    /// any still-warming portion is removed from the cluster's activating
+    /// total directly.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` the `StakingAccount` to close
+    /// 1. `[writable]` destination account to receive its lamports
+    /// 2. `[signer]` authority: the stake's owner or its lockup custodian
+    /// 3. `[]` Clock sysvar
+    /// 4. `[writable]` the shared `StakeHistory` account
+    Withdraw,
+
+    /// 5: Relax (or tighten) a `StakingAccount`'s lockup. Only the current
+    /// custodian may call this.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` the `StakingAccount`
+    /// 1. `[signer]` the current lockup custodian
+    RelaxLockup {
+        unlock_epoch: u64,
+        unlock_timestamp: i64,
+    },
+}
@@ -0,0 +1,9 @@
+//! On-chain staking and epoch-based reward distribution program.
+
+pub mod error;
+pub mod instruction;
+pub mod processor;
+pub mod state;
+
+#[cfg(not(feature = "no-entrypoint"))]
+mod entrypoint;
@@ -0,0 +1,339 @@
+//! Instruction processing for the staking/reward-distribution program.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::Sysvar,
+};
+
+use crate::{
+    error::StakingError,
+    instruction::StakingInstruction,
+    state::{partition_for_staker, Lockup, RewardPool, StakeHistory, StakingAccount},
+};
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let instruction = StakingInstruction::try_from_slice(instruction_data)
+        .map_err(|_| StakingError::InvalidInstructionData)?;
+
+    match instruction {
+        StakingInstruction::CreateStakingAccount {
+            stake_amount,
+            unlock_epoch,
+            unlock_timestamp,
+            custodian,
+        } => process_create_staking_account(
+            accounts,
+            stake_amount,
+            unlock_epoch,
+            unlock_timestamp,
+            custodian,
+        ),
+        StakingInstruction::InitializeRewardPool {
+            total_rewards,
+            num_partitions,
+            operator,
+            commission,
+        } => process_initialize_reward_pool(
+            accounts,
+            total_rewards,
+            num_partitions,
+            operator,
+            commission,
+        ),
+        StakingInstruction::DistributeRewards => process_distribute_rewards(accounts),
+        StakingInstruction::DistributeEpochPartition { partition_index } => {
+            process_distribute_epoch_partition(accounts, partition_index)
+        }
+        StakingInstruction::Withdraw => process_withdraw(accounts),
+        StakingInstruction::RelaxLockup {
+            unlock_epoch,
+            unlock_timestamp,
+        } => process_relax_lockup(accounts, unlock_epoch, unlock_timestamp),
+    }
+}
+
+fn process_create_staking_account(
+    accounts: &[AccountInfo],
+    stake_amount: u64,
+    unlock_epoch: u64,
+    unlock_timestamp: i64,
+    custodian: Pubkey,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let staking_account_info = next_account_info(account_info_iter)?;
+    let user_info = next_account_info(account_info_iter)?;
+    let stake_history_info = next_account_info(account_info_iter)?;
+    let clock_info = next_account_info(account_info_iter)?;
+    let reward_pool_info = next_account_info(account_info_iter)?;
+
+    let clock = Clock::from_account_info(clock_info)?;
+    let reward_pool = RewardPool::try_from_slice(&reward_pool_info.data.borrow())?;
+
+    let mut staking_account = StakingAccount {
+        is_initialized: true,
+        owner: *user_info.key,
+        stake_amount,
+        activation_epoch: clock.epoch,
+        deactivation_epoch: StakingAccount::NO_DEACTIVATION,
+        lockup: Lockup {
+            unlock_epoch,
+            unlock_timestamp,
+            custodian,
+        },
+        point_value_observed: reward_pool.cumulative_point_value,
+    };
+    staking_account.serialize(&mut &mut staking_account_info.data.borrow_mut()[..])?;
+
+    let mut stake_history = StakeHistory::try_from_slice(&stake_history_info.data.borrow())?;
+    stake_history.add_activating(clock.epoch, stake_amount);
+    stake_history.serialize(&mut &mut stake_history_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+/// Return a stake's full lamport balance to `destination` and close it,
+/// provided the caller is authorized per [`StakingInstruction::Withdraw`].
+///
+/// Before closing, whatever portion of the stake had already become
+/// effective begins cooldown in the shared `StakeHistory` (so the cluster
+/// total ramps it down the same way it ramped it in, instead of staying
+/// inflated forever); any portion still warming up is removed from the
+/// cluster's activating total outright, since it was never entitled to a
+/// share of rewards in the first place.
+fn process_withdraw(accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let staking_account_info = next_account_info(account_info_iter)?;
+    let destination_info = next_account_info(account_info_iter)?;
+    let authority_info = next_account_info(account_info_iter)?;
+    let clock_info = next_account_info(account_info_iter)?;
+    let stake_history_info = next_account_info(account_info_iter)?;
+
+    if !authority_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let staking_account = StakingAccount::try_from_slice(&staking_account_info.data.borrow())?;
+    if !staking_account.is_initialized {
+        return Err(StakingError::NotInitialized.into());
+    }
+
+    let clock = Clock::from_account_info(clock_info)?;
+
+    let is_custodian = *authority_info.key == staking_account.lockup.custodian;
+    if !is_custodian {
+        if *authority_info.key != staking_account.owner {
+            return Err(StakingError::WithdrawAuthorityMismatch.into());
+        }
+        if !staking_account
+            .lockup
+            .is_expired(clock.epoch, clock.unix_timestamp)
+        {
+            return Err(StakingError::LockupNotExpired.into());
+        }
+    }
+
+    let mut stake_history = StakeHistory::try_from_slice(&stake_history_info.data.borrow())?;
+    stake_history.advance_to_epoch(clock.epoch);
+    let effective = staking_account.effective_stake(&stake_history, clock.epoch);
+    if effective > 0 {
+        stake_history.add_deactivating(clock.epoch, effective);
+    }
+    let still_warming = staking_account.stake_amount - effective;
+    if still_warming > 0 {
+        stake_history.remove_activating(clock.epoch, still_warming);
+    }
+    stake_history.serialize(&mut &mut stake_history_info.data.borrow_mut()[..])?;
+
+    let lamports = staking_account_info.lamports();
+    **staking_account_info.try_borrow_mut_lamports()? -= lamports;
+    **destination_info.try_borrow_mut_lamports()? += lamports;
+    staking_account_info.data.borrow_mut().fill(0);
+
+    Ok(())
+}
+
+/// Let the current custodian relax (or tighten) a stake's lockup.
+fn process_relax_lockup(
+    accounts: &[AccountInfo],
+    unlock_epoch: u64,
+    unlock_timestamp: i64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let staking_account_info = next_account_info(account_info_iter)?;
+    let custodian_info = next_account_info(account_info_iter)?;
+
+    if !custodian_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let mut staking_account = StakingAccount::try_from_slice(&staking_account_info.data.borrow())?;
+    if !staking_account.is_initialized {
+        return Err(StakingError::NotInitialized.into());
+    }
+    if *custodian_info.key != staking_account.lockup.custodian {
+        return Err(StakingError::CustodianMismatch.into());
+    }
+
+    staking_account.lockup.unlock_epoch = unlock_epoch;
+    staking_account.lockup.unlock_timestamp = unlock_timestamp;
+    staking_account.serialize(&mut &mut staking_account_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+fn process_initialize_reward_pool(
+    accounts: &[AccountInfo],
+    total_rewards: u64,
+    num_partitions: u32,
+    operator: Pubkey,
+    commission: u8,
+) -> ProgramResult {
+    if commission > 100 {
+        return Err(StakingError::InvalidCommission.into());
+    }
+    if num_partitions == 0 {
+        return Err(StakingError::InvalidPartitionCount.into());
+    }
+
+    let account_info_iter = &mut accounts.iter();
+    let reward_pool_info = next_account_info(account_info_iter)?;
+
+    let reward_pool = RewardPool {
+        is_initialized: true,
+        total_rewards,
+        num_partitions,
+        operator,
+        commission,
+        distribution_epoch: RewardPool::NO_DISTRIBUTION,
+        distribution_cursor: 0,
+        distributable_rewards: 0,
+        total_effective_stake: 0,
+        cumulative_point_value: 0,
+    };
+    reward_pool.serialize(&mut &mut reward_pool_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+/// Legacy single-shot distribution: every staker is paid in one
+/// transaction. Only valid while the pool has a single partition; larger
+/// staker sets must crank through [`process_distribute_epoch_partition`].
+fn process_distribute_rewards(accounts: &[AccountInfo]) -> ProgramResult {
+    let reward_pool_info = accounts
+        .first()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let reward_pool = RewardPool::try_from_slice(&reward_pool_info.data.borrow())?;
+    if reward_pool.num_partitions != 1 {
+        return Err(StakingError::DistributeRewardsRequiresSinglePartition.into());
+    }
+
+    process_distribute_epoch_partition(accounts, 0)
+}
+
+/// Pay out exactly one partition of stakers for the current epoch's
+/// distribution, snapshotting the pool's totals first if this is the first
+/// call observed for a new epoch.
+fn process_distribute_epoch_partition(accounts: &[AccountInfo], partition_index: u32) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reward_pool_info = next_account_info(account_info_iter)?;
+    let stake_history_info = next_account_info(account_info_iter)?;
+    let clock_info = next_account_info(account_info_iter)?;
+    let operator_info = next_account_info(account_info_iter)?;
+
+    let clock = Clock::from_account_info(clock_info)?;
+    let mut stake_history = StakeHistory::try_from_slice(&stake_history_info.data.borrow())?;
+    // Replay any epochs that elapsed with no stake activity so the cluster
+    // totals (and therefore every staker's effective stake) are current.
+    stake_history.advance_to_epoch(clock.epoch);
+    stake_history.serialize(&mut &mut stake_history_info.data.borrow_mut()[..])?;
+    let mut reward_pool = RewardPool::try_from_slice(&reward_pool_info.data.borrow())?;
+
+    if !reward_pool.is_initialized {
+        return Err(StakingError::NotInitialized.into());
+    }
+    if *operator_info.key != reward_pool.operator {
+        return Err(StakingError::OperatorMismatch.into());
+    }
+    if reward_pool.total_rewards == 0 {
+        return Err(StakingError::InsufficientRewardPool.into());
+    }
+
+    if reward_pool.distribution_epoch != clock.epoch {
+        let previous_epoch_done = reward_pool.distribution_epoch == RewardPool::NO_DISTRIBUTION
+            || reward_pool.distribution_cursor == reward_pool.num_partitions;
+        if !previous_epoch_done {
+            return Err(StakingError::PreviousDistributionIncomplete.into());
+        }
+
+        let total_effective_stake = stake_history.entry(clock.epoch).map_or(0, |e| e.effective);
+        let commission_amount = (reward_pool.total_rewards as u128
+            * reward_pool.commission as u128
+            / 100) as u64;
+        **reward_pool_info.try_borrow_mut_lamports()? -= commission_amount;
+        **operator_info.try_borrow_mut_lamports()? += commission_amount;
+
+        let distributable_rewards = reward_pool.total_rewards - commission_amount;
+        let point_value = if total_effective_stake > 0 {
+            (distributable_rewards as u128 * RewardPool::POINT_VALUE_SCALE
+                / total_effective_stake as u128) as u64
+        } else {
+            0
+        };
+
+        reward_pool.distribution_epoch = clock.epoch;
+        reward_pool.distribution_cursor = 0;
+        reward_pool.total_effective_stake = total_effective_stake;
+        reward_pool.distributable_rewards = distributable_rewards;
+        reward_pool.cumulative_point_value += point_value as u128;
+    }
+
+    if partition_index != reward_pool.distribution_cursor {
+        return Err(StakingError::PartitionOutOfOrder.into());
+    }
+    if reward_pool.total_effective_stake == 0 {
+        return Err(StakingError::InsufficientRewardPool.into());
+    }
+
+    loop {
+        let staking_account_info = match next_account_info(account_info_iter) {
+            Ok(info) => info,
+            Err(_) => break,
+        };
+        let user_info = next_account_info(account_info_iter)?;
+        let mut staking_account =
+            StakingAccount::try_from_slice(&staking_account_info.data.borrow())?;
+        if staking_account.owner != *user_info.key {
+            return Err(StakingError::AccountMismatch.into());
+        }
+        if partition_for_staker(user_info.key, clock.epoch, reward_pool.num_partitions)
+            != partition_index
+        {
+            return Err(StakingError::StakerNotInPartition.into());
+        }
+
+        let effective = staking_account.effective_stake(&stake_history, clock.epoch);
+        let point_value_delta =
+            reward_pool.cumulative_point_value - staking_account.point_value_observed;
+        let reward = (effective as u128 * point_value_delta / RewardPool::POINT_VALUE_SCALE) as u64;
+
+        staking_account.point_value_observed = reward_pool.cumulative_point_value;
+        staking_account.serialize(&mut &mut staking_account_info.data.borrow_mut()[..])?;
+
+        **reward_pool_info.try_borrow_mut_lamports()? -= reward;
+        **user_info.try_borrow_mut_lamports()? += reward;
+    }
+
+    reward_pool.distribution_cursor += 1;
+    reward_pool.serialize(&mut &mut reward_pool_info.data.borrow_mut()[..])?;
+
+    Ok(())
+}
@@ -0,0 +1,295 @@
+//! Program account layouts for the staking/reward-distribution system.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+/// Number of epochs of cluster-wide stake totals retained in [`StakeHistory`].
+///
+/// This bounds how far back a stake's warmup/cooldown curve can be replayed;
+/// an account whose activation (or deactivation) epoch has fallen outside
+/// this window is treated as fully settled (fully effective, or fully
+/// inactive), same as the real `StakeHistory` sysvar.
+pub const MAX_STAKE_HISTORY_ENTRIES: usize = 64;
+
+/// Fraction of the cluster's effective stake that may transition from
+/// activating/deactivating to effective/inactive in a single epoch,
+/// expressed as `1 / WARMUP_COOLDOWN_RATE_DENOMINATOR` (25%, matching
+/// mainnet-beta's warmup/cooldown rate).
+pub const WARMUP_COOLDOWN_RATE_DENOMINATOR: u64 = 4;
+
+/// Cluster-wide activating/deactivating/effective stake totals as of the end
+/// of a single epoch.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StakeHistoryEntry {
+    pub epoch: u64,
+    pub effective: u64,
+    pub activating: u64,
+    pub deactivating: u64,
+}
+
+/// Shared, program-owned account tracking cluster-wide stake totals per
+/// epoch, analogous to the `StakeHistory` sysvar on mainnet-beta. A staking
+/// account's effective stake is always derived from this rather than stored
+/// directly, so warmup/cooldown curves stay correct no matter when an
+/// account is queried.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug)]
+pub struct StakeHistory {
+    pub is_initialized: bool,
+    /// The most recent epoch for which an entry has been recorded.
+    pub latest_epoch: u64,
+    /// Ring buffer indexed by `epoch % MAX_STAKE_HISTORY_ENTRIES`.
+    pub entries: [StakeHistoryEntry; MAX_STAKE_HISTORY_ENTRIES],
+}
+
+impl StakeHistory {
+    fn slot(epoch: u64) -> usize {
+        (epoch % MAX_STAKE_HISTORY_ENTRIES as u64) as usize
+    }
+
+    /// The recorded entry for `epoch`, if it's still within the retained window.
+    pub fn entry(&self, epoch: u64) -> Option<&StakeHistoryEntry> {
+        let entry = &self.entries[Self::slot(epoch)];
+        if entry.epoch == epoch {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    /// Replay cluster-wide warmup/cooldown transitions forward from
+    /// `latest_epoch` up to (and including) `target_epoch`, writing one
+    /// entry per epoch. Each epoch, at most `1 / WARMUP_COOLDOWN_RATE_DENOMINATOR`
+    /// of the prior epoch's effective stake may transition in from
+    /// activating, and symmetrically out to deactivating; when there is no
+    /// effective stake yet to anchor the rate (bootstrap), the full
+    /// activating amount transitions in one epoch.
+    pub fn advance_to_epoch(&mut self, target_epoch: u64) {
+        if !self.is_initialized {
+            self.is_initialized = true;
+            self.latest_epoch = target_epoch;
+            self.entries[Self::slot(target_epoch)] = StakeHistoryEntry {
+                epoch: target_epoch,
+                ..StakeHistoryEntry::default()
+            };
+            return;
+        }
+
+        let mut epoch = self.latest_epoch;
+        while epoch < target_epoch {
+            let prior = *self.entry(epoch).unwrap_or(&StakeHistoryEntry::default());
+            let next_epoch = epoch + 1;
+
+            let activating_delta = warmup_cooldown_delta(prior.effective, prior.activating);
+            let deactivating_delta = warmup_cooldown_delta(prior.effective, prior.deactivating);
+
+            let next = StakeHistoryEntry {
+                epoch: next_epoch,
+                effective: prior.effective + activating_delta - deactivating_delta,
+                activating: prior.activating - activating_delta,
+                deactivating: prior.deactivating - deactivating_delta,
+            };
+            self.entries[Self::slot(next_epoch)] = next;
+            epoch = next_epoch;
+        }
+        self.latest_epoch = self.latest_epoch.max(target_epoch);
+    }
+
+    /// Add `amount` to the activating total for `epoch`, first replaying
+    /// history up to `epoch` so the cluster totals are current.
+    pub fn add_activating(&mut self, epoch: u64, amount: u64) {
+        self.advance_to_epoch(epoch);
+        self.entries[Self::slot(epoch)].activating += amount;
+    }
+
+    /// Add `amount` to the deactivating total for `epoch`, first replaying
+    /// history up to `epoch` so the cluster totals are current. Symmetric to
+    /// `add_activating`: the amount ramps out of `effective` over subsequent
+    /// epochs via the same `WARMUP_COOLDOWN_RATE_DENOMINATOR` cap it ramped
+    /// in with.
+    pub fn add_deactivating(&mut self, epoch: u64, amount: u64) {
+        self.advance_to_epoch(epoch);
+        self.entries[Self::slot(epoch)].deactivating += amount;
+    }
+
+    /// Remove `amount` from the activating total for `epoch`, first replaying
+    /// history up to `epoch` so the cluster totals are current. Used when a
+    /// still-warming stake is withdrawn before becoming effective, so the
+    /// amount it would have contributed stops ramping in at all rather than
+    /// inflating `activating` (and eventually `effective`) forever.
+    pub fn remove_activating(&mut self, epoch: u64, amount: u64) {
+        self.advance_to_epoch(epoch);
+        self.entries[Self::slot(epoch)].activating -= amount;
+    }
+}
+
+/// The amount that transitions between activating/deactivating and
+/// effective in one epoch, given the prior epoch's effective total and the
+/// activating (or deactivating) total to draw from.
+fn warmup_cooldown_delta(prior_effective: u64, prior_transitioning: u64) -> u64 {
+    if prior_transitioning == 0 {
+        return 0;
+    }
+    let allowed = if prior_effective == 0 {
+        prior_transitioning
+    } else {
+        prior_effective / WARMUP_COOLDOWN_RATE_DENOMINATOR
+    };
+    allowed.min(prior_transitioning)
+}
+
+/// Replay an individual stake's warmup curve against the cluster-wide
+/// `stake_history`, returning its effective stake as of `target_epoch`.
+///
+/// Mirrors `advance_to_epoch`'s cluster-level recurrence, but pro-rates the
+/// cluster's per-epoch allowed transition across this stake's own remaining
+/// activating amount (`remaining / cluster_activating * allowed_delta`).
+pub fn calculate_effective_stake(
+    activation_epoch: u64,
+    stake_amount: u64,
+    stake_history: &StakeHistory,
+    target_epoch: u64,
+) -> u64 {
+    if target_epoch <= activation_epoch {
+        return 0;
+    }
+
+    let mut remaining_activating = stake_amount;
+    let mut epoch = activation_epoch + 1;
+    while epoch <= target_epoch && remaining_activating > 0 {
+        let prior = match stake_history.entry(epoch - 1) {
+            Some(entry) => *entry,
+            // Outside the retained history window: enough epochs have
+            // passed that the stake must already be fully effective.
+            None => {
+                remaining_activating = 0;
+                break;
+            }
+        };
+
+        let allowed_delta = warmup_cooldown_delta(prior.effective, prior.activating);
+        let account_delta = if prior.activating == 0 {
+            0
+        } else {
+            ((remaining_activating as u128) * (allowed_delta as u128) / (prior.activating as u128))
+                as u64
+        };
+
+        remaining_activating -= account_delta;
+        epoch += 1;
+    }
+
+    stake_amount - remaining_activating
+}
+
+/// Restricts withdrawal of a [`StakingAccount`] until both an epoch and a
+/// wall-clock time have passed, mirroring the native stake program's
+/// lockup. `custodian` may bypass the restriction entirely, and is the only
+/// account allowed to relax (or tighten) it later.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Lockup {
+    pub unlock_epoch: u64,
+    pub unlock_timestamp: i64,
+    pub custodian: Pubkey,
+}
+
+impl Lockup {
+    /// Whether a non-custodian withdrawal is allowed as of `epoch`/`unix_timestamp`.
+    pub fn is_expired(&self, epoch: u64, unix_timestamp: i64) -> bool {
+        epoch >= self.unlock_epoch && unix_timestamp >= self.unlock_timestamp
+    }
+}
+
+/// A single user's staking position.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug)]
+pub struct StakingAccount {
+    pub is_initialized: bool,
+    pub owner: Pubkey,
+    pub stake_amount: u64,
+    /// Epoch at which this stake began warming up. Effective stake is zero
+    /// until the epoch after this one.
+    pub activation_epoch: u64,
+    /// Epoch at which this stake began cooling down, or `u64::MAX` while
+    /// still active.
+    pub deactivation_epoch: u64,
+    pub lockup: Lockup,
+    /// `RewardPool::cumulative_point_value` as of the last distribution this
+    /// account was paid out in (or as of account creation, if never paid).
+    /// Only the point value accrued since then is owed on the next
+    /// distribution, which makes payout idempotent and independent of when
+    /// it's claimed, and correct even if several distributions (each at its
+    /// own rate) were skipped in between.
+    pub point_value_observed: u128,
+}
+
+impl StakingAccount {
+    pub const NO_DEACTIVATION: u64 = u64::MAX;
+
+    /// This stake's effective amount as of `current_epoch`, per the
+    /// warmup/cooldown curve recorded in `stake_history`.
+    pub fn effective_stake(&self, stake_history: &StakeHistory, current_epoch: u64) -> u64 {
+        calculate_effective_stake(
+            self.activation_epoch,
+            self.stake_amount,
+            stake_history,
+            current_epoch,
+        )
+    }
+}
+
+/// The shared pool rewards are distributed from.
+///
+/// Distribution for a large staker set is partitioned across many
+/// transactions: the first call of a new epoch snapshots `distributable_rewards`
+/// and `total_effective_stake`, then each call pays out exactly one partition
+/// (`distribution_cursor`) and advances it, so a crank can spread the work
+/// across many blocks instead of one compute-limited transaction.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug)]
+pub struct RewardPool {
+    pub is_initialized: bool,
+    pub total_rewards: u64,
+    pub num_partitions: u32,
+    /// Account that receives `commission` of `total_rewards` before the
+    /// pro-rata staker split, each epoch.
+    pub operator: Pubkey,
+    /// Percentage (0-100) of `total_rewards` skimmed to `operator`.
+    pub commission: u8,
+    /// Epoch the current snapshot belongs to, or [`RewardPool::NO_DISTRIBUTION`]
+    /// before the first distribution has ever been started.
+    pub distribution_epoch: u64,
+    /// Index of the next partition to be paid. Equal to `num_partitions`
+    /// once the current epoch's distribution is complete.
+    pub distribution_cursor: u32,
+    /// `total_rewards` for the epoch minus the operator's commission; what
+    /// actually gets split pro-rata across stakers.
+    pub distributable_rewards: u64,
+    /// Cluster-wide effective stake for `distribution_epoch`, snapshotted
+    /// from `StakeHistory` so every partition's payout uses a consistent total.
+    pub total_effective_stake: u64,
+    /// Running sum of every epoch's own point value (lamports owed per unit
+    /// of effective stake, fixed-point scaled by [`RewardPool::POINT_VALUE_SCALE`])
+    /// since the pool's creation. A staker's reward is
+    /// `effective * (cumulative_point_value - StakingAccount::point_value_observed)`,
+    /// so a stake that joined late (or was already paid this epoch) doesn't
+    /// earn anything it wasn't present for, and a stake that skips one or
+    /// more distributions before claiming is still paid each skipped epoch
+    /// at that epoch's own rate rather than the latest rate repeated.
+    pub cumulative_point_value: u128,
+}
+
+impl RewardPool {
+    pub const NO_DISTRIBUTION: u64 = u64::MAX;
+
+    /// Fixed-point scale for `point_value`, chosen so that
+    /// `distributable_rewards / total_effective_stake` retains enough
+    /// precision to avoid losing small payouts to integer truncation.
+    pub const POINT_VALUE_SCALE: u128 = 1_000_000;
+}
+
+/// Which partition `staker` falls into for `epoch`'s distribution. The seed
+/// is derived from the epoch number so the layout is stable within an epoch
+/// but reshuffles across epochs.
+pub fn partition_for_staker(staker: &Pubkey, epoch: u64, num_partitions: u32) -> u32 {
+    let hash = solana_program::hash::hashv(&[staker.as_ref(), &epoch.to_le_bytes()]);
+    let value = u64::from_le_bytes(hash.to_bytes()[0..8].try_into().unwrap());
+    (value % num_partitions as u64) as u32
+}
@@ -1,17 +1,25 @@
 use solana_program_test::*;
-use solana_sdk::{ 
+use solana_sdk::{
     account::Account,
+    clock::Clock,
+    epoch_schedule::EpochSchedule,
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
     signature::{Keypair, Signer},
+    sysvar,
     transaction::Transaction,
     transport::TransportError,
     system_instruction,
-}; 
+};
 use std::str::FromStr;
+use borsh::BorshDeserialize;
+use ontora_ai_program::instruction::StakingInstruction;
 use ontora_ai_program::processor::process_instruction;
-use ontora_ai_program::state::{StakingAccount, RewardPool};
+use ontora_ai_program::state::{
+    partition_for_staker, StakeHistory, StakingAccount, RewardPool, MAX_STAKE_HISTORY_ENTRIES,
+};
 
+const STAKE_HISTORY_SPACE: u64 = 1 + 8 + (MAX_STAKE_HISTORY_ENTRIES as u64) * 32;
 
 async fn setup_test_environment() -> Result<(ProgramTest, Keypair, Pubkey), TransportError> {
     let program_id = Pubkey::from_str("YourProgramIdHere11111111111111111111111111111").unwrap();
@@ -22,10 +30,6 @@ async fn setup_test_environment() -> Result<(ProgramTest, Keypair, Pubkey), Tran
         processor!(process_instruction),
     );
 
-    Lightning Scheduler      │ Event Channels (lock-free) │ ring buffers
-    $Socode
-    )}
-
     program_test.add_account(
         payer.pubkey(),
         Account {
@@ -40,18 +44,83 @@ async fn setup_test_environment() -> Result<(ProgramTest, Keypair, Pubkey), Tran
     Ok((program_test, payer, program_id))
 }
 
+/// Allocate the shared `StakeHistory` account. Its contents are lazily
+/// initialized by the program the first time a stake is created against it.
+async fn create_stake_history(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    program_id: &Pubkey,
+) -> Result<Pubkey, TransportError> {
+    let stake_history = Keypair::new();
+    let rent = banks_client.get_rent().await.unwrap();
+    let lamports = rent.minimum_balance(STAKE_HISTORY_SPACE as usize);
+
+    let create_account_ix = system_instruction::create_account(
+        &payer.pubkey(),
+        &stake_history.pubkey(),
+        lamports,
+        STAKE_HISTORY_SPACE,
+        program_id,
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[create_account_ix],
+        Some(&payer.pubkey()),
+        &[payer, &stake_history],
+        banks_client.get_latest_blockhash().await.unwrap(),
+    );
+
+    banks_client.process_transaction(tx).await?;
+
+    Ok(stake_history.pubkey())
+}
+
+/// Stakes with no lockup (unlocked from epoch/timestamp zero) against
+/// `reward_pool`, which is what most tests that don't care about withdrawal
+/// restrictions want.
 async fn create_staking_account(
     banks_client: &mut BanksClient,
     payer: &Keypair,
     program_id: &Pubkey,
     user: &Keypair,
+    stake_history: &Pubkey,
+    reward_pool: &Pubkey,
     stake_amount: u64,
 ) -> Result<Pubkey, TransportError> {
-    #[msg("Holder not active")]
+    create_staking_account_with_lockup(
+        banks_client,
+        payer,
+        program_id,
+        user,
+        stake_history,
+        reward_pool,
+        stake_amount,
+        0,
+        0,
+        Pubkey::default(),
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn create_staking_account_with_lockup(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    program_id: &Pubkey,
+    user: &Keypair,
+    stake_history: &Pubkey,
+    reward_pool: &Pubkey,
+    stake_amount: u64,
+    unlock_epoch: u64,
+    unlock_timestamp: i64,
+    custodian: Pubkey,
+) -> Result<Pubkey, TransportError> {
     let staking_account = Keypair::new();
     let rent = banks_client.get_rent().await.unwrap();
     let space = std::mem::size_of::<StakingAccount>() as u64;
-    let lamports = rent.minimum_balance(space as usize);
+    // Fund the account with the staked principal itself (not just the
+    // rent-exempt reserve), so withdrawal has real lamports to return.
+    let lamports = rent.minimum_balance(space as usize) + stake_amount;
 
     let create_account_ix = system_instruction::create_account(
         &payer.pubkey(),
@@ -59,34 +128,24 @@ async fn create_staking_account(
         lamports,
         space,
         program_id,
-
-        #[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(init, payer = user, space = 8 + 64)]
-    pub vault: Account<'info, Vault>,
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
-    $SOCODE
-    )}
-
-        Entropy ↑ → Collapse() → Compress() → Reform() → Balance →
-        $PURGESGI 
-        )}
-$LITCUT 
-        )}
     );
 
-$mEMODE
-    )}
-
     let stake_ix = Instruction {
         program_id: *program_id,
         accounts: vec![
             AccountMeta::new(staking_account.pubkey(), false),
             AccountMeta::new(user.pubkey(), true),
+            AccountMeta::new(*stake_history, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(*reward_pool, false),
         ],
-        data: vec![0, stake_amount as u8], // Mock instruction data for staking
+        data: borsh::to_vec(&StakingInstruction::CreateStakingAccount {
+            stake_amount,
+            unlock_epoch,
+            unlock_timestamp,
+            custodian,
+        })
+        .unwrap(),
     };
 
     let tx = Transaction::new_signed_with_payer(
@@ -101,11 +160,56 @@ $mEMODE
     Ok(staking_account.pubkey())
 }
 
+fn withdraw_ix(
+    program_id: &Pubkey,
+    staking_account: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    stake_history: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*staking_account, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new(*stake_history, false),
+        ],
+        data: borsh::to_vec(&StakingInstruction::Withdraw).unwrap(),
+    }
+}
+
+/// Creates a reward pool with a single partition and no operator commission,
+/// returning `(reward_pool, operator)`. Most tests don't care about
+/// commission; they just need a valid operator account to pass in.
 async fn create_reward_pool(
     banks_client: &mut BanksClient,
     payer: &Keypair,
     program_id: &Pubkey,
     total_rewards: u64,
+) -> Result<(Pubkey, Pubkey), TransportError> {
+    let operator = Keypair::new();
+    let pool = create_reward_pool_with_partitions(
+        banks_client,
+        payer,
+        program_id,
+        total_rewards,
+        1,
+        &operator.pubkey(),
+        0,
+    ).await?;
+    Ok((pool, operator.pubkey()))
+}
+
+async fn create_reward_pool_with_partitions(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    program_id: &Pubkey,
+    total_rewards: u64,
+    num_partitions: u32,
+    operator: &Pubkey,
+    commission: u8,
 ) -> Result<Pubkey, TransportError> {
     let reward_pool = Keypair::new();
     let rent = banks_client.get_rent().await.unwrap();
@@ -123,7 +227,13 @@ async fn create_reward_pool(
     let init_pool_ix = Instruction {
         program_id: *program_id,
         accounts: vec![AccountMeta::new(reward_pool.pubkey(), false)],
-        data: vec![1], // Mock instruction data for initializing reward pool
+        data: borsh::to_vec(&StakingInstruction::InitializeRewardPool {
+            total_rewards,
+            num_partitions,
+            operator: *operator,
+            commission,
+        })
+        .unwrap(),
     };
 
     let tx = Transaction::new_signed_with_payer(
@@ -138,10 +248,82 @@ async fn create_reward_pool(
     Ok(reward_pool.pubkey())
 }
 
+fn distribute_ix(
+    program_id: &Pubkey,
+    reward_pool: &Pubkey,
+    stake_history: &Pubkey,
+    operator: &Pubkey,
+    stakers: &[(Pubkey, Pubkey)],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*reward_pool, false),
+        AccountMeta::new(*stake_history, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new(*operator, false),
+    ];
+    for (staking_account, user) in stakers {
+        accounts.push(AccountMeta::new(*staking_account, false));
+        accounts.push(AccountMeta::new(*user, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: borsh::to_vec(&StakingInstruction::DistributeRewards).unwrap(),
+    }
+}
+
+fn distribute_partition_ix(
+    program_id: &Pubkey,
+    reward_pool: &Pubkey,
+    stake_history: &Pubkey,
+    operator: &Pubkey,
+    stakers: &[(Pubkey, Pubkey)],
+    partition_index: u32,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*reward_pool, false),
+        AccountMeta::new(*stake_history, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new(*operator, false),
+    ];
+    for (staking_account, user) in stakers {
+        accounts.push(AccountMeta::new(*staking_account, false));
+        accounts.push(AccountMeta::new(*user, false));
+    }
+
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: borsh::to_vec(&StakingInstruction::DistributeEpochPartition { partition_index })
+            .unwrap(),
+    }
+}
+
+/// Warp the banks client's clock forward to the first slot of the next epoch,
+/// returning the epoch that is now current.
+async fn advance_epoch(context: &mut ProgramTestContext) -> u64 {
+    advance_epochs(context, 1).await
+}
+
+/// Warp the banks client's clock forward `n` epochs from whatever epoch it's
+/// currently at, returning the epoch that is now current. Re-fetches the
+/// blockhash afterward so the next transaction built against `context`
+/// doesn't reuse one that predates the warp.
+async fn advance_epochs(context: &mut ProgramTestContext, n: u64) -> u64 {
+    let epoch_schedule = context.banks_client.get_sysvar::<EpochSchedule>().await.unwrap();
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    let target_epoch = clock.epoch + n;
+    let target_slot = epoch_schedule.get_first_slot_in_epoch(target_epoch);
+    context.warp_to_slot(target_slot).unwrap();
+    context.last_blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    context.banks_client.get_sysvar::<Clock>().await.unwrap().epoch
+}
+
 #[tokio::test]
 async fn test_reward_distribution_basic() {
-    let (program_test, payer, program_id) = setup_test_environment().await.unwrap();
-    let mut banks_client = program_test.start().await;
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
 
     let user1 = Keypair::new();
     let user2 = Keypair::new();
@@ -149,55 +331,61 @@ async fn test_reward_distribution_basic() {
     let stake_amount2 = 200;
     let total_rewards = 30;
 
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let (reward_pool, operator) = create_reward_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        total_rewards,
+    ).await.unwrap();
+
     let staking_account1 = create_staking_account(
-        &mut banks_client,
-        &payer,
+        &mut context.banks_client,
+        &context.payer,
         &program_id,
         &user1,
+        &stake_history,
+        &reward_pool,
         stake_amount1,
     ).await.unwrap();
 
     let staking_account2 = create_staking_account(
-        &mut banks_client,
-        &payer,
+        &mut context.banks_client,
+        &context.payer,
         &program_id,
         &user2,
+        &stake_history,
+        &reward_pool,
         stake_amount2,
     ).await.unwrap();
 
-    let reward_pool = create_reward_pool(
-        &mut banks_client,
-        &payer,
-        &program_id,
-        total_rewards,
-    ).await.unwrap();
+    // Both stakes bootstrap to fully effective after one epoch (there is no
+    // prior effective stake to anchor the 25%/epoch cap against).
+    advance_epoch(&mut context).await;
 
-    let distribute_ix = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(reward_pool, false),
-            AccountMeta::new(staking_account1, false),
-            AccountMeta::new(staking_account2, false),
-            AccountMeta::new(user1.pubkey(), false),
-            AccountMeta::new(user2.pubkey(), false),
-        ],
-        data: vec![2], // Mock instruction data for reward distribution
-    };
-    litcut.capture(event_id, duration=20, mode="auto");
-)}
+    let distribute_ix = distribute_ix(
+        &program_id,
+        &reward_pool,
+        &stake_history,
+        &operator,
+        &[(staking_account1, user1.pubkey()), (staking_account2, user2.pubkey())],
+    );
 
     let tx = Transaction::new_signed_with_payer(
         &[distribute_ix],
-        Some(&payer.pubkey()),
-        &[&payer],
-        banks_client.get_latest_blockhash().await.unwrap(),
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
     );
 
-    let result = banks_client.process_transaction(tx).await;
+    let result = context.banks_client.process_transaction(tx).await;
     assert!(result.is_ok());
 
-    let user1_balance = banks_client.get_balance(user1.pubkey()).await.unwrap();
-    let user2_balance = banks_client.get_balance(user2.pubkey()).await.unwrap();
+    let user1_balance = context.banks_client.get_balance(user1.pubkey()).await.unwrap();
+    let user2_balance = context.banks_client.get_balance(user2.pubkey()).await.unwrap();
 
     assert_eq!(user1_balance, 10); // 1/3 of rewards (100/300 * 30)
     assert_eq!(user2_balance, 20); // 2/3 of rewards (200/300 * 30)
@@ -205,8 +393,8 @@ async fn test_reward_distribution_basic() {
 
 #[tokio::test]
 async fn test_reward_distribution_zero_stake() {
-    let (program_test, payer, program_id) = setup_test_environment().await.unwrap();
-    let mut banks_client = program_test.start().await;
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
 
     let user1 = Keypair::new();
     let user2 = Keypair::new();
@@ -214,53 +402,59 @@ async fn test_reward_distribution_zero_stake() {
     let stake_amount2 = 0;
     let total_rewards = 30;
 
-    let staking_account1 = create_staking_account( 
-        &mut banks_client,
-        &payer,
-        &program_id, $Cetian
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let (reward_pool, operator) = create_reward_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        total_rewards,
+    ).await.unwrap();
+
+    let staking_account1 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
         &user1,
+        &stake_history,
+        &reward_pool,
         stake_amount1,
     ).await.unwrap();
 
     let staking_account2 = create_staking_account(
-        &mut banks_client,
-        &payer,
+        &mut context.banks_client,
+        &context.payer,
         &program_id,
         &user2,
+        &stake_history,
+        &reward_pool,
         stake_amount2,
     ).await.unwrap();
 
-    let reward_pool = create_reward_pool(
-        &mut banks_client,
-        &payer,
-        &program_id,
-        total_rewards,
-    ).await.unwrap();
+    advance_epoch(&mut context).await;
 
-    let distribute_ix = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(reward_pool, false),
-            AccountMeta::new(staking_account1, false),
-            AccountMeta::new(staking_account2, false),
-            AccountMeta::new(user1.pubkey(), false),
-            AccountMeta::new(user2.pubkey(), false),
-        ],
-        data: vec![2],
-    };
+    let distribute_ix = distribute_ix(
+        &program_id,
+        &reward_pool,
+        &stake_history,
+        &operator,
+        &[(staking_account1, user1.pubkey()), (staking_account2, user2.pubkey())],
+    );
 
     let tx = Transaction::new_signed_with_payer(
         &[distribute_ix],
-        Some(&payer.pubkey()),
-        &[&payer],
-        banks_client.get_latest_blockhash().await.unwrap(),
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
     );
 
-    let result = banks_client.process_transaction(tx).await;
+    let result = context.banks_client.process_transaction(tx).await;
     assert!(result.is_ok());
 
-    let user1_balance = banks_client.get_balance(user1.pubkey()).await.unwrap();
-    let user2_balance = banks_client.get_balance(user2.pubkey()).await.unwrap();
+    let user1_balance = context.banks_client.get_balance(user1.pubkey()).await.unwrap();
+    let user2_balance = context.banks_client.get_balance(user2.pubkey()).await.unwrap();
 
     assert_eq!(user1_balance, 30); // All rewards go to user1
     assert_eq!(user2_balance, 0);  // No rewards for zero stake
@@ -268,53 +462,59 @@ async fn test_reward_distribution_zero_stake() {
 
 #[tokio::test]
 async fn test_reward_distribution_insufficient_pool() {
-    let (program_test, payer, program_id) = setup_test_environment().await.unwrap();
-    let mut banks_client = program_test.start().await;
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
 
     let user1 = Keypair::new();
     let stake_amount1 = 100;
     let total_rewards = 0;
 
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let (reward_pool, operator) = create_reward_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        total_rewards,
+    ).await.unwrap();
+
     let staking_account1 = create_staking_account(
-        &mut banks_client,
-        &payer,
+        &mut context.banks_client,
+        &context.payer,
         &program_id,
         &user1,
+        &stake_history,
+        &reward_pool,
         stake_amount1,
     ).await.unwrap();
 
-    let reward_pool = create_reward_pool(
-        &mut banks_client,
-        &payer,
-        &program_id,
-        total_rewards,
-    ).await.unwrap();
+    advance_epoch(&mut context).await;
 
-    let distribute_ix = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(reward_pool, false),
-            AccountMeta::new(staking_account1, false),
-            AccountMeta::new(user1.pubkey(), false),
-        ],
-        data: vec![2],
-    };
+    let distribute_ix = distribute_ix(
+        &program_id,
+        &reward_pool,
+        &stake_history,
+        &operator,
+        &[(staking_account1, user1.pubkey())],
+    );
 
     let tx = Transaction::new_signed_with_payer(
         &[distribute_ix],
-        Some(&payer.pubkey()),
-        &[&payer],
-        banks_client.get_latest_blockhash().await.unwrap(),
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
     );
 
-    let result = banks_client.process_transaction(tx).await;
+    let result = context.banks_client.process_transaction(tx).await;
     assert!(result.is_err()); // Should fail due to insufficient rewards
 }
 
 #[tokio::test]
 async fn test_reward_distribution_uneven_split() {
-    let (program_test, payer, program_id) = setup_test_environment().await.unwrap();
-    let mut banks_client = program_test.start().await;
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
 
     let user1 = Keypair::new();
     let user2 = Keypair::new();
@@ -322,150 +522,1147 @@ async fn test_reward_distribution_uneven_split() {
     let stake_amount2 = 2;
     let total_rewards = 10;
 
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let (reward_pool, operator) = create_reward_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        total_rewards,
+    ).await.unwrap();
+
     let staking_account1 = create_staking_account(
-        &mut banks_client,
-        &payer,
+        &mut context.banks_client,
+        &context.payer,
         &program_id,
         &user1,
+        &stake_history,
+        &reward_pool,
         stake_amount1,
     ).await.unwrap();
 
     let staking_account2 = create_staking_account(
-        &mut banks_client,
-        &payer,
+        &mut context.banks_client,
+        &context.payer,
         &program_id,
         &user2,
+        &stake_history,
+        &reward_pool,
         stake_amount2,
     ).await.unwrap();
 
-    let reward_pool = create_reward_pool(
-        &mut banks_client,
-        &payer,
+    advance_epoch(&mut context).await;
+
+    let distribute_ix = distribute_ix(
+        &program_id,
+        &reward_pool,
+        &stake_history,
+        &operator,
+        &[(staking_account1, user1.pubkey()), (staking_account2, user2.pubkey())],
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[distribute_ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+
+    let result = context.banks_client.process_transaction(tx).await;
+    assert!(result.is_ok());
+
+    let user1_balance = context.banks_client.get_balance(user1.pubkey()).await.unwrap();
+    let user2_balance = context.banks_client.get_balance(user2.pubkey()).await.unwrap();
+
+    // Both floor independently against the pool's point value, so a few
+    // lamports of dust are left in the pool rather than handed to either side.
+    assert_eq!(user1_balance, 3); // floor(1/3 of rewards)
+    assert_eq!(user2_balance, 6); // floor(2/3 of rewards)
+}
+
+#[tokio::test]
+async fn test_reward_distribution_unauthorized_access() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    let user1 = Keypair::new();
+    let unauthorized_user = Keypair::new();
+    let stake_amount1 = 100;
+    let total_rewards = 30;
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let (reward_pool, operator) = create_reward_pool(
+        &mut context.banks_client,
+        &context.payer,
         &program_id,
         total_rewards,
     ).await.unwrap();
 
-    let distribute_ix = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(reward_pool, false),
-            AccountMeta::new(staking_account1, false),
-            AccountMeta::new(staking_account2, false),
-            AccountMeta::new(user1.pubkey(), false),
-            AccountMeta::new(user2.pubkey(), false),
-        ],
-        data: vec![2],
-    };
+    let staking_account1 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user1,
+        &stake_history,
+        &reward_pool,
+        stake_amount1,
+    ).await.unwrap();
+
+    advance_epoch(&mut context).await;
+
+    // Wrong user paired with staking_account1's real owner.
+    let distribute_ix = distribute_ix(
+        &program_id,
+        &reward_pool,
+        &stake_history,
+        &operator,
+        &[(staking_account1, unauthorized_user.pubkey())],
+    );
 
     let tx = Transaction::new_signed_with_payer(
         &[distribute_ix],
-        Some(&payer.pubkey()),
-        &[&payer],
-        banks_client.get_latest_blockhash().await.unwrap(),
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+
+    let result = context.banks_client.process_transaction(tx).await;
+    assert!(result.is_err()); // Should fail due to unauthorized access
+}
+
+#[tokio::test]
+async fn test_reward_distribution_multiple_epochs() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    let user1 = Keypair::new();
+    let stake_amount1 = 100;
+    let total_rewards_per_epoch = 10;
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let (reward_pool, operator) = create_reward_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        total_rewards_per_epoch,
+    ).await.unwrap();
+
+    // `RewardPool::total_rewards` is the flat amount distributed *per*
+    // snapshotted epoch, not a draining total, so the pool's actual lamport
+    // balance must cover every epoch's payout, not just one.
+    let top_up_ix = system_instruction::transfer(
+        &context.payer.pubkey(),
+        &reward_pool,
+        total_rewards_per_epoch,
     );
+    let tx = Transaction::new_signed_with_payer(
+        &[top_up_ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let staking_account1 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user1,
+        &stake_history,
+        &reward_pool,
+        stake_amount1,
+    ).await.unwrap();
+
+    for _ in 0..2 {
+        advance_epoch(&mut context).await;
+
+        let distribute_ix = distribute_ix(
+            &program_id,
+            &reward_pool,
+            &stake_history,
+            &operator,
+        &[(staking_account1, user1.pubkey())],
+        );
+
+        let tx = Transaction::new_signed_with_payer(
+            &[distribute_ix],
+            Some(&context.payer.pubkey()),
+            &[&context.payer],
+            context.banks_client.get_latest_blockhash().await.unwrap(),
+        );
+
+        let result = context.banks_client.process_transaction(tx).await;
+        assert!(result.is_ok());
+    }
+
+    let user1_balance = context.banks_client.get_balance(user1.pubkey()).await.unwrap();
+    assert_eq!(user1_balance, 20); // Rewards accumulated over 2 epochs
+}
+
+/// Jumping multiple epochs in a single warp (rather than stepping through
+/// them one at a time) must still be picked up correctly: the program always
+/// reads the current epoch from the Clock sysvar at distribution time, not
+/// from however many individual transactions happened to run before it.
+#[tokio::test]
+async fn test_distribution_reads_current_epoch_after_multi_epoch_warp() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    let user1 = Keypair::new();
+    let stake_amount1 = 100;
+    let total_rewards = 30;
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let (reward_pool, operator) = create_reward_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        total_rewards,
+    ).await.unwrap();
 
-    let result = banks_client.process_transaction(tx).await;
+    let staking_account1 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user1,
+        &stake_history,
+        &reward_pool,
+        stake_amount1,
+    ).await.unwrap();
+
+    // Jump five epochs in one warp instead of one at a time; the sole
+    // staker still bootstraps to fully effective the epoch after activation,
+    // with no other activity against stake_history in between.
+    let epoch = advance_epochs(&mut context, 5).await;
+
+    let distribute_ix = distribute_ix(
+        &program_id,
+        &reward_pool,
+        &stake_history,
+        &operator,
+        &[(staking_account1, user1.pubkey())],
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[distribute_ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    let result = context.banks_client.process_transaction(tx).await;
     assert!(result.is_ok());
 
-    let user1_balance = banks_client.get_balance(user1.pubkey()).await.unwrap();
-    let user2_balance = banks_client.get_balance(user2.pubkey()).await.unwrap();
+    let reward_pool_data = context.banks_client.get_account(reward_pool).await.unwrap().unwrap();
+    let pool = RewardPool::try_from_slice(&reward_pool_data.data).unwrap();
+    assert_eq!(
+        pool.distribution_epoch, epoch,
+        "distribution must snapshot the Clock's current epoch, not a stale one"
+    );
 
-    assert_eq!(user1_balance, 3); // Approx 1/3 of rewards (rounded)
-    assert_eq!(user2_balance, 7); // Approx 2/3 of rewards (rounded)
+    let user1_balance = context.banks_client.get_balance(user1.pubkey()).await.unwrap();
+    assert_eq!(user1_balance, total_rewards); // sole staker gets everything
 }
 
+/// Stakes while the cluster already has effective stake to anchor the
+/// warmup rate against (i.e. not the bootstrap case), and asserts the
+/// second stake's effective amount never decreases epoch over epoch and
+/// converges on the full staked amount.
 #[tokio::test]
-async fn test_reward_distribution_unauthorized_access() {
-    let (program_test, payer, program_id) = setup_test_environment().await.unwrap();
-    let mut banks_client = program_test.start().await;
+async fn test_warmup_curve_converges_to_full_stake() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    let user1 = Keypair::new();
+    let user2 = Keypair::new();
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+    let (reward_pool, operator) = create_reward_pool(&mut context.banks_client, &context.payer, &program_id, 0)
+        .await
+        .unwrap();
+    let _ = operator;
+
+    // user1 bootstraps the cluster's effective stake so user2's warmup below
+    // is governed by the 25%/epoch cap rather than the bootstrap shortcut.
+    let staking_account1 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user1,
+        &stake_history,
+        &reward_pool,
+        1_000,
+    ).await.unwrap();
+    advance_epoch(&mut context).await;
+
+    let staking_account2 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user2,
+        &stake_history,
+        &reward_pool,
+        1_000,
+    ).await.unwrap();
+    let _ = staking_account1;
+
+    let mut last_effective = 0u64;
+    for _ in 0..10 {
+        let epoch = advance_epoch(&mut context).await;
+
+        // Nothing else touches `stake_history` this epoch; poke it forward
+        // with a zero-amount stake so the cluster totals (and therefore
+        // user2's replayed curve below) reflect the current epoch, exactly
+        // as the real StakeHistory sysvar would without any new activity.
+        create_staking_account(
+            &mut context.banks_client,
+            &context.payer,
+            &program_id,
+            &Keypair::new(),
+            &stake_history,
+            &reward_pool,
+            0,
+        ).await.unwrap();
+
+        let stake_history_account = context
+            .banks_client
+            .get_account(stake_history)
+            .await
+            .unwrap()
+            .unwrap();
+        let history = StakeHistory::try_from_slice(&stake_history_account.data).unwrap();
+        let staking_account2_data = context
+            .banks_client
+            .get_account(staking_account2)
+            .await
+            .unwrap()
+            .unwrap();
+        let account2 = StakingAccount::try_from_slice(&staking_account2_data.data).unwrap();
+
+        let effective = account2.effective_stake(&history, epoch);
+        assert!(effective >= last_effective, "effective stake must not regress");
+        last_effective = effective;
+    }
+
+    assert_eq!(last_effective, 1_000, "stake must fully converge given enough epochs");
+}
+
+/// Cranks a reward pool's distribution across every partition and asserts
+/// the summed payouts reconcile with the pool total (minus rounding dust).
+#[tokio::test]
+async fn test_partitioned_distribution_sums_to_pool_total() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    const NUM_STAKERS: usize = 6;
+    const NUM_PARTITIONS: u32 = 3;
+    const TOTAL_REWARDS: u64 = 600;
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let operator = Keypair::new();
+    let reward_pool = create_reward_pool_with_partitions(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        TOTAL_REWARDS,
+        NUM_PARTITIONS,
+        &operator.pubkey(),
+        0,
+    ).await.unwrap();
+
+    let mut users = Vec::new();
+    let mut staking_accounts = Vec::new();
+    for i in 0..NUM_STAKERS {
+        let user = Keypair::new();
+        let stake_amount = 100 * (i as u64 + 1);
+        let staking_account = create_staking_account(
+            &mut context.banks_client,
+            &context.payer,
+            &program_id,
+            &user,
+            &stake_history,
+            &reward_pool,
+            stake_amount,
+        ).await.unwrap();
+        staking_accounts.push(staking_account);
+        users.push(user);
+    }
+
+    // All stakes bootstrap to fully effective after one epoch.
+    let epoch = advance_epoch(&mut context).await;
+
+    let mut by_partition: Vec<Vec<(Pubkey, Pubkey)>> = vec![Vec::new(); NUM_PARTITIONS as usize];
+    for (staking_account, user) in staking_accounts.iter().zip(users.iter()) {
+        let partition = partition_for_staker(&user.pubkey(), epoch, NUM_PARTITIONS);
+        by_partition[partition as usize].push((*staking_account, user.pubkey()));
+    }
+
+    for (partition_index, stakers) in by_partition.iter().enumerate() {
+        let ix = distribute_partition_ix(
+            &program_id,
+            &reward_pool,
+            &stake_history,
+            &operator.pubkey(),
+            stakers,
+            partition_index as u32,
+        );
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&context.payer.pubkey()),
+            &[&context.payer],
+            context.banks_client.get_latest_blockhash().await.unwrap(),
+        );
+        let result = context.banks_client.process_transaction(tx).await;
+        assert!(result.is_ok(), "partition {partition_index} should pay out");
+    }
+
+    // Re-paying a partition already covered by the cursor must fail.
+    let replay_ix = distribute_partition_ix(&program_id, &reward_pool, &stake_history, &operator.pubkey(), &[], 0);
+    let tx = Transaction::new_signed_with_payer(
+        &[replay_ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    assert!(context.banks_client.process_transaction(tx).await.is_err());
+
+    let mut total_paid = 0u64;
+    for user in &users {
+        total_paid += context.banks_client.get_balance(user.pubkey()).await.unwrap();
+    }
+
+    assert!(total_paid <= TOTAL_REWARDS);
+    assert!(TOTAL_REWARDS - total_paid < NUM_STAKERS as u64); // rounding dust only
+}
+
+/// Stakes two equal stakers, distributes with the given `commission`, and
+/// returns `(operator_balance, user1_balance, user2_balance)`.
+async fn run_commission_scenario(commission: u8) -> (u64, u64, u64) {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    let user1 = Keypair::new();
+    let user2 = Keypair::new();
+    let operator = Keypair::new();
+    let total_rewards = 100;
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let reward_pool = create_reward_pool_with_partitions(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        total_rewards,
+        1,
+        &operator.pubkey(),
+        commission,
+    ).await.unwrap();
+
+    let staking_account1 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user1,
+        &stake_history,
+        &reward_pool,
+        100,
+    ).await.unwrap();
+
+    let staking_account2 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user2,
+        &stake_history,
+        &reward_pool,
+        100,
+    ).await.unwrap();
+
+    advance_epoch(&mut context).await;
+
+    let ix = distribute_ix(
+        &program_id,
+        &reward_pool,
+        &stake_history,
+        &operator.pubkey(),
+        &[(staking_account1, user1.pubkey()), (staking_account2, user2.pubkey())],
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    let result = context.banks_client.process_transaction(tx).await;
+    assert!(result.is_ok());
+
+    (
+        context.banks_client.get_balance(operator.pubkey()).await.unwrap(),
+        context.banks_client.get_balance(user1.pubkey()).await.unwrap(),
+        context.banks_client.get_balance(user2.pubkey()).await.unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_commission_zero_percent_goes_entirely_to_stakers() {
+    let (operator_balance, user1_balance, user2_balance) = run_commission_scenario(0).await;
+    assert_eq!(operator_balance, 0);
+    assert_eq!(user1_balance + user2_balance, 100);
+}
+
+#[tokio::test]
+async fn test_commission_fifty_percent_splits_evenly_with_operator() {
+    let (operator_balance, user1_balance, user2_balance) = run_commission_scenario(50).await;
+    assert_eq!(operator_balance, 50);
+    assert_eq!(user1_balance, 25);
+    assert_eq!(user2_balance, 25);
+    assert_eq!(operator_balance + user1_balance + user2_balance, 100);
+}
+
+#[tokio::test]
+async fn test_commission_hundred_percent_goes_entirely_to_operator() {
+    let (operator_balance, user1_balance, user2_balance) = run_commission_scenario(100).await;
+    assert_eq!(operator_balance, 100);
+    assert_eq!(user1_balance, 0);
+    assert_eq!(user2_balance, 0);
+}
+
+/// Including the same staking account twice in a single distribution call
+/// pays it once: after the first payout its `point_value_observed` catches up
+/// to the pool's `cumulative_point_value`, so the second entry's point value
+/// delta (and therefore its reward) is zero.
+#[tokio::test]
+async fn test_point_value_observed_makes_repeat_payout_within_call_a_noop() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
 
     let user1 = Keypair::new();
-    let unauthorized_user = Keypair::new();
     let stake_amount1 = 100;
     let total_rewards = 30;
 
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let (reward_pool, operator) = create_reward_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        total_rewards,
+    ).await.unwrap();
+
     let staking_account1 = create_staking_account(
-        &mut banks_client,
-        &payer,
+        &mut context.banks_client,
+        &context.payer,
         &program_id,
         &user1,
+        &stake_history,
+        &reward_pool,
         stake_amount1,
     ).await.unwrap();
 
-    let reward_pool = create_reward_pool(
-        &mut banks_client,
-        &payer,
+    advance_epoch(&mut context).await;
+
+    // staking_account1 appears twice in the same call's staker list.
+    let distribute_ix = distribute_ix(
+        &program_id,
+        &reward_pool,
+        &stake_history,
+        &operator,
+        &[(staking_account1, user1.pubkey()), (staking_account1, user1.pubkey())],
+    );
+
+    let tx = Transaction::new_signed_with_payer(
+        &[distribute_ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+
+    let result = context.banks_client.process_transaction(tx).await;
+    assert!(result.is_ok());
+
+    let user1_balance = context.banks_client.get_balance(user1.pubkey()).await.unwrap();
+    assert_eq!(user1_balance, 30); // paid once, not twice
+}
+
+/// A staking account created after a distribution has already been
+/// snapshotted seeds `point_value_observed` from the pool's current
+/// `cumulative_point_value`, so it doesn't retroactively earn rewards for
+/// epochs that elapsed before it existed.
+#[tokio::test]
+async fn test_freshly_created_stake_seeds_point_value_observed_to_pool_value() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    let user1 = Keypair::new();
+    let user2 = Keypair::new();
+    let stake_amount1 = 100;
+    let total_rewards = 30;
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let (reward_pool, operator) = create_reward_pool(
+        &mut context.banks_client,
+        &context.payer,
         &program_id,
         total_rewards,
     ).await.unwrap();
 
-    let distribute_ix = Instruction {
-        program_id,
-        accounts: vec![
-            AccountMeta::new(reward_pool, false),
-            AccountMeta::new(staking_account1, false),
-            AccountMeta::new(unauthorized_user.pubkey(), false), // Wrong user
-        ],
-        data: vec![2],
-    };
+    let staking_account1 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user1,
+        &stake_history,
+        &reward_pool,
+        stake_amount1,
+    ).await.unwrap();
+
+    advance_epoch(&mut context).await;
 
+    let distribute_ix = distribute_ix(
+        &program_id,
+        &reward_pool,
+        &stake_history,
+        &operator,
+        &[(staking_account1, user1.pubkey())],
+    );
     let tx = Transaction::new_signed_with_payer(
         &[distribute_ix],
-        Some(&payer.pubkey()),
-        &[&payer],
-        banks_client.get_latest_blockhash().await.unwrap(),
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
     );
+    context.banks_client.process_transaction(tx).await.unwrap();
 
-    let result = banks_client.process_transaction(tx).await;
-    assert!(result.is_err()); // Should fail due to unauthorized access
+    let reward_pool_data = context.banks_client.get_account(reward_pool).await.unwrap().unwrap();
+    let pool_after_first_distribution = RewardPool::try_from_slice(&reward_pool_data.data).unwrap();
+    assert!(pool_after_first_distribution.cumulative_point_value > 0);
+
+    // user2 joins only now, after one epoch's worth of point value has
+    // already accrued to the pool.
+    let staking_account2 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user2,
+        &stake_history,
+        &reward_pool,
+        100,
+    ).await.unwrap();
+
+    let staking_account2_data = context.banks_client.get_account(staking_account2).await.unwrap().unwrap();
+    let account2 = StakingAccount::try_from_slice(&staking_account2_data.data).unwrap();
+    assert_eq!(
+        account2.point_value_observed, pool_after_first_distribution.cumulative_point_value,
+        "a freshly created stake must not owe rewards for epochs that elapsed before it existed"
+    );
 }
 
+/// Across several epochs of point-value-based distribution, the sum paid to
+/// all stakers never exceeds what the pool actually holds, even though each
+/// epoch's point value is floored independently.
 #[tokio::test]
-async fn test_reward_distribution_multiple_epochs() {
-    let (program_test, payer, program_id) = setup_test_environment().await.unwrap();
-    let mut banks_client = program_test.start().await;
+async fn test_point_value_accounting_sum_never_exceeds_pool_across_epochs() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
 
+    const NUM_EPOCHS: u64 = 3;
     let user1 = Keypair::new();
+    let user2 = Keypair::new();
     let stake_amount1 = 100;
-    let total_rewards_per_epoch = 10;
+    let stake_amount2 = 200;
+    let total_rewards_per_epoch = 30;
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+
+    let (reward_pool, operator) = create_reward_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        total_rewards_per_epoch,
+    ).await.unwrap();
+
+    // `total_rewards` is the flat per-epoch rate, not a draining total (see
+    // test_reward_distribution_multiple_epochs), so top up for the extra epochs.
+    let top_up_ix = system_instruction::transfer(
+        &context.payer.pubkey(),
+        &reward_pool,
+        total_rewards_per_epoch * (NUM_EPOCHS - 1),
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[top_up_ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
 
     let staking_account1 = create_staking_account(
-        &mut banks_client,
-        &payer,
+        &mut context.banks_client,
+        &context.payer,
         &program_id,
         &user1,
+        &stake_history,
+        &reward_pool,
         stake_amount1,
     ).await.unwrap();
 
-    let reward_pool = create_reward_pool(
-        &mut banks_client,
-        &payer,
+    let staking_account2 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
         &program_id,
-        total_rewards_per_epoch * 2,
+        &user2,
+        &stake_history,
+        &reward_pool,
+        stake_amount2,
     ).await.unwrap();
 
-    for _ in 0..2 {
-        let distribute_ix = Instruction {
-            program_id,
-            accounts: vec![
-                AccountMeta::new(reward_pool, false),
-                AccountMeta::new(staking_account1, false),
-                AccountMeta::new(user1.pubkey(), false),
-            ],
-            data: vec![2],
-        };
+    for _ in 0..NUM_EPOCHS {
+        advance_epoch(&mut context).await;
 
+        let distribute_ix = distribute_ix(
+            &program_id,
+            &reward_pool,
+            &stake_history,
+            &operator,
+            &[(staking_account1, user1.pubkey()), (staking_account2, user2.pubkey())],
+        );
         let tx = Transaction::new_signed_with_payer(
             &[distribute_ix],
-            Some(&payer.pubkey()),
-            &[&payer],
-            banks_client.get_latest_blockhash().await.unwrap(),
+            Some(&context.payer.pubkey()),
+            &[&context.payer],
+            context.banks_client.get_latest_blockhash().await.unwrap(),
         );
+        context.banks_client.process_transaction(tx).await.unwrap();
+    }
 
-        let result = banks_client.process_transaction(tx).await;
-        assert!(result.is_ok());
+    let user1_balance = context.banks_client.get_balance(user1.pubkey()).await.unwrap();
+    let user2_balance = context.banks_client.get_balance(user2.pubkey()).await.unwrap();
+    let total_paid = user1_balance + user2_balance;
+
+    assert!(total_paid <= total_rewards_per_epoch * NUM_EPOCHS);
+    assert!(total_rewards_per_epoch * NUM_EPOCHS - total_paid < NUM_EPOCHS); // rounding dust only
+}
+
+/// A staker that skips a distribution (isn't included in that epoch's call)
+/// and is paid a later epoch must be credited each skipped epoch at that
+/// epoch's own point value, not the latest epoch's rate repeated once per
+/// skipped epoch.
+#[tokio::test]
+async fn test_staker_that_skips_a_distribution_is_paid_each_epochs_own_rate() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    let user1 = Keypair::new();
+    let total_rewards_per_epoch = 1_000;
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+    let (reward_pool, operator) = create_reward_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        total_rewards_per_epoch,
+    ).await.unwrap();
+
+    let staking_account1 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user1,
+        &stake_history,
+        &reward_pool,
+        1_000,
+    ).await.unwrap();
+
+    // user1 bootstraps to fully effective (no prior effective stake to anchor
+    // the 25%/epoch cap against).
+    advance_epoch(&mut context).await;
+
+    // Epoch 1's distribution snapshots at total_effective_stake == 1_000
+    // (point value == 1.0 reward per unit), but user1 is left out of the
+    // staker list, so it isn't paid yet.
+    let skip_ix = distribute_ix(&program_id, &reward_pool, &stake_history, &operator, &[]);
+    let tx = Transaction::new_signed_with_payer(
+        &[skip_ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // A second staker joins after epoch 1's snapshot. Governed by the
+    // 25%/epoch cap (cluster effective is already 1_000), only 250 of its
+    // 1_000 can ramp in during epoch 2, growing total_effective_stake to
+    // 1_250 and therefore lowering epoch 2's point value below epoch 1's.
+    create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &Keypair::new(),
+        &stake_history,
+        &reward_pool,
+        1_000,
+    ).await.unwrap();
+    advance_epoch(&mut context).await;
+
+    // Fund epoch 2's distribution on top of what epoch 1 left unclaimed.
+    let top_up_ix = system_instruction::transfer(&context.payer.pubkey(), &reward_pool, total_rewards_per_epoch);
+    let tx = Transaction::new_signed_with_payer(
+        &[top_up_ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    // Now claim: user1 is owed epoch 1's rate (1_000 * 1_000/1_000 == 1_000)
+    // plus epoch 2's rate (1_000 * 1_000/1_250 == 800), for 1_800 total. The
+    // conflated formula would instead charge epoch 2's rate twice (1_600).
+    let claim_ix = distribute_ix(
+        &program_id,
+        &reward_pool,
+        &stake_history,
+        &operator,
+        &[(staking_account1, user1.pubkey())],
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[claim_ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let user1_balance = context.banks_client.get_balance(user1.pubkey()).await.unwrap();
+    assert_eq!(user1_balance, 1_800, "must be paid each skipped epoch at that epoch's own rate");
+}
+
+/// Withdrawing before a stake's lockup has expired, signed only by its
+/// owner, must fail.
+#[tokio::test]
+async fn test_withdraw_before_lockup_expiry_fails() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    let user = Keypair::new();
+    let custodian = Keypair::new();
+    let current_epoch = context.banks_client.get_sysvar::<Clock>().await.unwrap().epoch;
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+    let (reward_pool, _operator) = create_reward_pool(&mut context.banks_client, &context.payer, &program_id, 0)
+        .await
+        .unwrap();
+    let staking_account = create_staking_account_with_lockup(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user,
+        &stake_history,
+        &reward_pool,
+        1_000,
+        current_epoch + 5,
+        0,
+        custodian.pubkey(),
+    ).await.unwrap();
+
+    let ix = withdraw_ix(&program_id, &staking_account, &user.pubkey(), &user.pubkey(), &stake_history);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &user],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    let result = context.banks_client.process_transaction(tx).await;
+    assert!(result.is_err());
+}
+
+/// Once the lockup's unlock epoch has passed, the owner can withdraw the
+/// full account balance and the account is closed.
+#[tokio::test]
+async fn test_withdraw_after_lockup_expiry_succeeds() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    let user = Keypair::new();
+    let custodian = Keypair::new();
+    let current_epoch = context.banks_client.get_sysvar::<Clock>().await.unwrap().epoch;
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+    let (reward_pool, _operator) = create_reward_pool(&mut context.banks_client, &context.payer, &program_id, 0)
+        .await
+        .unwrap();
+    let staking_account = create_staking_account_with_lockup(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user,
+        &stake_history,
+        &reward_pool,
+        1_000,
+        current_epoch + 1,
+        0,
+        custodian.pubkey(),
+    ).await.unwrap();
+
+    let staking_account_balance = context.banks_client.get_balance(staking_account).await.unwrap();
+    advance_epoch(&mut context).await;
+
+    let ix = withdraw_ix(&program_id, &staking_account, &user.pubkey(), &user.pubkey(), &stake_history);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &user],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    let result = context.banks_client.process_transaction(tx).await;
+    assert!(result.is_ok());
+
+    let user_balance = context.banks_client.get_balance(user.pubkey()).await.unwrap();
+    assert_eq!(user_balance, staking_account_balance);
+}
+
+/// The custodian can withdraw on the owner's behalf at any time, even while
+/// the lockup is nowhere close to expiring.
+#[tokio::test]
+async fn test_withdraw_via_custodian_override_ignores_lockup() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    let user = Keypair::new();
+    let custodian = Keypair::new();
+    let current_epoch = context.banks_client.get_sysvar::<Clock>().await.unwrap().epoch;
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+    let (reward_pool, _operator) = create_reward_pool(&mut context.banks_client, &context.payer, &program_id, 0)
+        .await
+        .unwrap();
+    let staking_account = create_staking_account_with_lockup(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user,
+        &stake_history,
+        &reward_pool,
+        1_000,
+        current_epoch + 1_000,
+        i64::MAX,
+        custodian.pubkey(),
+    ).await.unwrap();
+
+    let staking_account_balance = context.banks_client.get_balance(staking_account).await.unwrap();
+
+    let ix = withdraw_ix(&program_id, &staking_account, &user.pubkey(), &custodian.pubkey(), &stake_history);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &custodian],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    let result = context.banks_client.process_transaction(tx).await;
+    assert!(result.is_ok());
+
+    let user_balance = context.banks_client.get_balance(user.pubkey()).await.unwrap();
+    assert_eq!(user_balance, staking_account_balance);
+}
+
+/// Withdrawing a fully-effective stake begins cooldown rather than just
+/// vanishing: the cluster's effective total in `StakeHistory` ramps back
+/// down over subsequent epochs via the same 25%/epoch cap warmup uses,
+/// instead of staying permanently inflated by a stake that's already been
+/// paid out and closed.
+#[tokio::test]
+async fn test_withdraw_begins_cluster_cooldown() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+
+    let user = Keypair::new();
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+    let (reward_pool, _operator) = create_reward_pool(&mut context.banks_client, &context.payer, &program_id, 0)
+        .await
+        .unwrap();
+    let staking_account = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user,
+        &stake_history,
+        &reward_pool,
+        1_000,
+    ).await.unwrap();
+
+    // Bootstrap to fully effective before withdrawing.
+    advance_epoch(&mut context).await;
+
+    let ix = withdraw_ix(&program_id, &staking_account, &user.pubkey(), &user.pubkey(), &stake_history);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &user],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let mut last_effective = 1_000u64;
+    for _ in 0..5 {
+        let epoch = advance_epoch(&mut context).await;
+
+        // Nothing else touches `stake_history` this epoch; poke it forward
+        // with a zero-amount stake, as in test_warmup_curve_converges_to_full_stake.
+        create_staking_account(
+            &mut context.banks_client,
+            &context.payer,
+            &program_id,
+            &Keypair::new(),
+            &stake_history,
+            &reward_pool,
+            0,
+        ).await.unwrap();
+
+        let stake_history_account = context
+            .banks_client
+            .get_account(stake_history)
+            .await
+            .unwrap()
+            .unwrap();
+        let history = StakeHistory::try_from_slice(&stake_history_account.data).unwrap();
+        let effective = history.entry(epoch).map_or(0, |e| e.effective);
+
+        assert!(
+            effective < last_effective,
+            "withdrawn stake must ramp down via cooldown, not stay inflated forever"
+        );
+        last_effective = effective;
     }
+}
 
-    let user1_balance = banks_client.get_balance(user1.pubkey()).await.unwrap();
-    assert_eq!(user1_balance, 20); // Rewards accumulated over 2 epochs
+#[tokio::test]
+async fn test_initialize_reward_pool_rejects_zero_partitions() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+    let operator = Keypair::new();
+
+    let result = create_reward_pool_with_partitions(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        100,
+        0,
+        &operator.pubkey(),
+        0,
+    ).await;
+
+    assert!(result.is_err(), "a reward pool must have at least one partition");
+}
+
+#[tokio::test]
+async fn test_withdraw_before_fully_warmed_does_not_inflate_cluster_total() {
+    let (program_test, _payer, program_id) = setup_test_environment().await.unwrap();
+    let mut context = program_test.start_with_context().await;
+    let user1 = Keypair::new();
+    let user2 = Keypair::new();
+
+    let stake_history = create_stake_history(&mut context.banks_client, &context.payer, &program_id)
+        .await
+        .unwrap();
+    let (reward_pool, operator) = create_reward_pool(&mut context.banks_client, &context.payer, &program_id, 100)
+        .await
+        .unwrap();
+
+    let staking_account1 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user1,
+        &stake_history,
+        &reward_pool,
+        1_000,
+    ).await.unwrap();
+
+    // Withdraw user1 in the same epoch it was created, before any of its
+    // stake has become effective: it is still entirely in `activating`.
+    let ix = withdraw_ix(&program_id, &staking_account1, &user1.pubkey(), &user1.pubkey(), &stake_history);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &user1],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let staking_account2 = create_staking_account(
+        &mut context.banks_client,
+        &context.payer,
+        &program_id,
+        &user2,
+        &stake_history,
+        &reward_pool,
+        1_000,
+    ).await.unwrap();
+
+    // user2 bootstraps to fully effective after one epoch; user1's withdrawn
+    // stake must not still be ramping in alongside it.
+    let epoch = advance_epoch(&mut context).await;
+
+    let distribute_ix = distribute_ix(
+        &program_id,
+        &reward_pool,
+        &stake_history,
+        &operator,
+        &[(staking_account2, user2.pubkey())],
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[distribute_ix],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    context.banks_client.process_transaction(tx).await.unwrap();
+
+    let stake_history_account = context
+        .banks_client
+        .get_account(stake_history)
+        .await
+        .unwrap()
+        .unwrap();
+    let history = StakeHistory::try_from_slice(&stake_history_account.data).unwrap();
+    let total_effective = history.entry(epoch).map_or(0, |e| e.effective);
+
+    assert_eq!(
+        total_effective, 1_000,
+        "a withdrawn, still-warming stake must not keep inflating the cluster's activating/effective total"
+    );
 }